@@ -0,0 +1,25 @@
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use napi_modules::{EnvExt, ImportAttributes};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct Config {
+    name: String,
+}
+
+#[napi]
+pub async fn import_json_name(env: Env) -> napi::Result<String> {
+    let path = env.dirname()?.join("fixture.json");
+    let config: Config = env.import_json(path.as_str()).await?;
+    Ok(config.name)
+}
+
+#[napi]
+pub async fn import_with_name(env: Env) -> napi::Result<String> {
+    let path = env.dirname()?.join("fixture.json");
+    let attributes = ImportAttributes { r#type: Some("json".to_string()) };
+    let module = env.import_with(path.as_str(), attributes)?.await?;
+    let default: Object = module.get_named_property("default")?;
+    default.get_named_property("name")
+}
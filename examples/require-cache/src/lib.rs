@@ -0,0 +1,21 @@
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use napi_modules::EnvExt;
+
+#[napi]
+pub fn read_value(env: Env) -> napi::Result<u32> {
+    let module: Object = env.require("./fixture.js")?;
+    module.get_named_property("value")
+}
+
+#[napi]
+pub fn invalidate_fixture(env: Env) -> napi::Result<()> {
+    env.invalidate("./fixture.js");
+    Ok(())
+}
+
+#[napi]
+pub fn invalidate_all_fixtures(env: Env) -> napi::Result<()> {
+    env.invalidate_all();
+    Ok(())
+}
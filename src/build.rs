@@ -0,0 +1,32 @@
+//! Helpers for build scripts and integration tests that need to turn a
+//! compiled `cdylib` artifact into a loadable `.node` addon.
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+/// Renames the `cdylib` artifact produced by building `crate_name` under
+/// `profile` (e.g. `"debug"` or `"release"`) into a `<lib_name>.node` file
+/// next to it (`lib_name` is `crate_name` with `-` replaced by `_`), using the
+/// host target's artifact naming convention, and returns the path to the
+/// renamed file.
+///
+/// `target_dir` is the Cargo target directory containing `<profile>/`, e.g.
+/// `"target"` when run from a workspace root, or `std::env::var("CARGO_TARGET_DIR")`
+/// / a path derived from `OUT_DIR` when called from a `build.rs` (whose
+/// working directory is the package's manifest directory, not the workspace
+/// root).
+pub fn link_node_addon(crate_name: &str, profile: &str, target_dir: &Utf8Path) -> std::io::Result<Utf8PathBuf> {
+    let lib_name = crate_name.replace('-', "_");
+    let (prefix, extension) = if cfg!(target_os = "windows") {
+        ("", "dll")
+    } else if cfg!(target_os = "macos") {
+        ("lib", "dylib")
+    } else {
+        ("lib", "so")
+    };
+    let profile_dir = target_dir.join(profile);
+    let artifact_path = profile_dir.join(format!("{prefix}{lib_name}.{extension}"));
+    let node_path = profile_dir.join(format!("{lib_name}.node"));
+    _ = fs_err::remove_file(&node_path);
+    fs_err::rename(&artifact_path, &node_path)?;
+    Ok(node_path)
+}
@@ -1,23 +1,40 @@
-use camino::Utf8PathBuf;
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
 use derive_more::{From, Into};
 use memoize::memoize;
 use napi::bindgen_prelude::*;
+use serde::de::DeserializeOwned;
 use thiserror::Error;
 use url::{ParseError, Url};
-use std::{hash::{Hash, Hasher}, rc::Rc, result::Result};
+use std::{cell::RefCell, collections::HashMap, hash::{Hash, Hasher}, rc::Rc, result::Result};
+
+pub mod build;
 
 pub(crate) trait Sealed {}
 
 impl Sealed for Env {}
 
+/// Import attributes for the `with { ... }` clause of a dynamic `import()`.
+#[derive(Debug, Clone, Default)]
+pub struct ImportAttributes {
+    pub r#type: Option<String>,
+}
+
 #[allow(private_bounds)]
 pub trait EnvExt: Sealed {
     fn filename(&self) -> napi::Result<Utf8PathBuf>;
+    fn dirname(&self) -> napi::Result<Utf8PathBuf>;
     fn require<T: FromNapiValue>(&self, id: impl AsRef<str>) -> napi::Result<T>;
+    fn require_relative<T: FromNapiValue>(&self, id: impl AsRef<str>) -> napi::Result<T>;
     fn require_resolve(&self, id: impl AsRef<str>) -> napi::Result<String>;
+    fn invalidate(&self, id: &str);
+    fn invalidate_all(&self);
     fn import(&self, specifier: impl AsRef<str>, options: Option<Object>) -> napi::Result<Promise<Object<'_>>>;
+    fn import_relative(&self, id: impl AsRef<str>, options: Option<Object>) -> napi::Result<Promise<Object<'_>>>;
+    fn import_with(&self, specifier: impl AsRef<str>, attributes: ImportAttributes) -> napi::Result<Promise<Object<'_>>>;
+    async fn import_json<T: DeserializeOwned>(&self, specifier: impl AsRef<str>) -> napi::Result<T>;
     fn import_meta_resolve(&self, specifier: impl AsRef<str>) -> napi::Result<String>;
     fn is_main(&self) -> napi::Result<bool>;
+    fn resolve_specifier(&self, specifier: &str, base: Option<&Utf8Path>) -> napi::Result<Utf8PathBuf>;
 }
 
 impl EnvExt for Env {
@@ -26,25 +43,66 @@ impl EnvExt for Env {
         let path = file_url_string_to_utf8_path_buf(&file_url_string).map_err(|e| napi::Error::from_reason(e.to_string()))?;
         Ok(path)
     }
+    fn dirname(&self) -> napi::Result<Utf8PathBuf> {
+        let filename = self.filename()?;
+        let dir = filename
+            .parent()
+            .ok_or_else(|| napi::Error::from_reason("filename has no parent directory"))?;
+        Ok(dir.to_owned())
+    }
     fn require<T: FromNapiValue>(&self, id: impl AsRef<str>) -> napi::Result<T> {
-        let require = require_for(self.clone().into()).map_err(|e| napi::Error::from_reason(e))?;
-        let require = require.borrow_back(self)?;
-        let module = require.call(id.as_ref())?;
+        let module_ref = resolved_module_for(self, id.as_ref())?;
+        let module = module_ref.borrow_back(self)?;
         let module: T = unsafe { module.cast()? };
         Ok(module)
     }
+    fn require_relative<T: FromNapiValue>(&self, id: impl AsRef<str>) -> napi::Result<T> {
+        let id = self.dirname()?.join(id.as_ref());
+        self.require(id.as_str())
+    }
     fn require_resolve(&self, id: impl AsRef<str>) -> napi::Result<String> {
         let require = require_for(self.clone().into()).map_err(|e| napi::Error::from_reason(e))?;
         let require = require.borrow_back(self)?;
         let require_resolve: Function<&str, String> = require.get_named_property("resolve")?;
         require_resolve.call(id.as_ref())
     }
+    fn invalidate(&self, id: &str) {
+        let key = (EnvEqHash::from(self.clone()), id.to_owned());
+        REQUIRE_CACHE.with(|cache| {
+            cache.borrow_mut().remove(&key);
+        });
+    }
+    fn invalidate_all(&self) {
+        let env = EnvEqHash::from(self.clone());
+        REQUIRE_CACHE.with(|cache| {
+            cache.borrow_mut().retain(|(cached_env, _), _| *cached_env != env);
+        });
+    }
     fn import(&self, specifier: impl AsRef<str>, options: Option<Object>) -> napi::Result<Promise<Object<'_>>> {
         let esm_helpers = esm_helpers_for(self.clone().into()).map_err(|e| napi::Error::from_reason(e))?;
         let esm_helpers = esm_helpers.get_value(self)?;
         let import: Function<FnArgs<(&str, Option<Object>)>, Promise<Object>> = esm_helpers.get_named_property("import")?;
         import.call((specifier.as_ref(), options).into())
     }
+    fn import_relative(&self, id: impl AsRef<str>, options: Option<Object>) -> napi::Result<Promise<Object<'_>>> {
+        let specifier = self.dirname()?.join(id.as_ref());
+        self.import(specifier.as_str(), options)
+    }
+    fn import_with(&self, specifier: impl AsRef<str>, attributes: ImportAttributes) -> napi::Result<Promise<Object<'_>>> {
+        let options = Object::new(self)?;
+        options.set_named_property("with", import_attributes_object(self, &attributes)?)?;
+        self.import(specifier, Some(options))
+    }
+    async fn import_json<T: DeserializeOwned>(&self, specifier: impl AsRef<str>) -> napi::Result<T> {
+        let esm_helpers = esm_helpers_for(self.clone().into()).map_err(|e| napi::Error::from_reason(e))?;
+        let esm_helpers = esm_helpers.get_value(self)?;
+        let import_default: Function<FnArgs<(&str, Object)>, Promise<Unknown>> = esm_helpers.get_named_property("importDefault")?;
+        let attributes = ImportAttributes { r#type: Some("json".to_string()) };
+        let options = Object::new(self)?;
+        options.set_named_property("with", import_attributes_object(self, &attributes)?)?;
+        let value = import_default.call((specifier.as_ref(), options).into())?.await?;
+        self.from_js_value(value)
+    }
     fn import_meta_resolve(&self, specifier: impl AsRef<str>) -> napi::Result<String> {
         let esm_helpers = esm_helpers_for(self.clone().into()).map_err(|e| napi::Error::from_reason(e))?;
         let esm_helpers = esm_helpers.get_value(self)?;
@@ -64,6 +122,63 @@ impl EnvExt for Env {
             Ok(false)
         }
     }
+    fn resolve_specifier(&self, specifier: &str, base: Option<&Utf8Path>) -> napi::Result<Utf8PathBuf> {
+        let referrer = self.filename()?;
+        let referrer_dir = referrer.parent().unwrap_or(Utf8Path::new("/"));
+        let joined = if specifier.starts_with("./") || specifier.starts_with("../") {
+            referrer_dir.join(specifier)
+        } else {
+            let candidate = Utf8Path::new(specifier);
+            if !candidate.is_absolute() {
+                return Err(napi::Error::from_reason(ResolveSpecifierError::NotPathShaped.to_string()));
+            }
+            candidate.to_path_buf()
+        };
+        let normalized = lexically_normalize(&joined);
+        if let Some(base) = base {
+            let normalized_base = lexically_normalize(base);
+            if !normalized.starts_with(&normalized_base) {
+                return Err(napi::Error::from_reason(ResolveSpecifierError::OutsideBase.to_string()));
+            }
+        }
+        Ok(normalized)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+enum ResolveSpecifierError {
+    #[error("resolved path escapes base directory")]
+    OutsideBase,
+    #[error("specifier is neither relative ('./' or '../') nor an absolute path")]
+    NotPathShaped,
+}
+
+fn lexically_normalize(path: &Utf8Path) -> Utf8PathBuf {
+    let mut stack: Vec<Utf8Component> = Vec::new();
+    for component in path.components() {
+        match component {
+            Utf8Component::CurDir => {}
+            Utf8Component::ParentDir => match stack.last() {
+                Some(Utf8Component::Normal(_)) => {
+                    stack.pop();
+                }
+                // Climbing past the root/prefix is a no-op rather than retaining a literal `..`.
+                Some(Utf8Component::RootDir) | Some(Utf8Component::Prefix(_)) => {}
+                _ => stack.push(component),
+            },
+            _ => stack.push(component),
+        }
+    }
+    stack.into_iter().collect()
+}
+
+fn import_attributes_object<'env>(env: &'env Env, attributes: &ImportAttributes) -> napi::Result<Object<'env>> {
+    let object = Object::new(env)?;
+    if let Some(ty) = &attributes.r#type {
+        object.set_named_property("type", ty.as_str())?;
+    }
+    Ok(object)
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Error)]
@@ -79,35 +194,50 @@ fn esm_helpers_path_for(addon_path: Utf8PathBuf) -> Result<Utf8PathBuf, EsmHelpe
         const _import = (specifier, options) => import(specifier, options);
         export { _import as "import" };
         export const importMetaResolve = (specifier) => import.meta.resolve(specifier);
+        export const importDefault = async (specifier, options) => {
+            const module = await import(specifier, options);
+            return module.default;
+        };
     "#;
     let esm_helpers_path = addon_path.with_added_extension("esm-helpers.js");
     fs_err::write(&esm_helpers_path, ESM_HELPERS_JS).map_err(|e| EsmHelpersPathForError::IoError(e.to_string()))?;
     Ok(esm_helpers_path)
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+/// Error converting between a `file:` URL and a [`Utf8PathBuf`]/[`Utf8Path`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
 #[non_exhaustive]
-enum FileUrlStringToUtf8PathBufError {
+pub enum FileUrlConversionError {
     #[error("parse error: {0}")]
     UrlParseError(#[from] ParseError),
     #[error("scheme is not 'file'")]
     SchemeNotFile,
-    #[error("to_file_path failed")]
-    ToFilePathFailed,
+    #[error("could not convert between a file path and a file URL")]
+    ConversionFailed,
     #[error("path is not valid UTF-8")]
     PathNotUtf8,
 }
 
-fn file_url_string_to_utf8_path_buf(file_url_string: &str) -> Result<Utf8PathBuf, FileUrlStringToUtf8PathBufError> {
-    let url = Url::parse(file_url_string).map_err(FileUrlStringToUtf8PathBufError::UrlParseError)?;
+/// Parses a `file:` URL (as produced by Node's `import.meta.url` or
+/// `createRequire`) into an absolute [`Utf8PathBuf`].
+pub fn file_url_string_to_utf8_path_buf(file_url_string: &str) -> Result<Utf8PathBuf, FileUrlConversionError> {
+    let url = Url::parse(file_url_string).map_err(FileUrlConversionError::UrlParseError)?;
     if url.scheme() != "file" {
-        return Err(FileUrlStringToUtf8PathBufError::SchemeNotFile);
+        return Err(FileUrlConversionError::SchemeNotFile);
     }
-    let path = url.to_file_path().map_err(|_| FileUrlStringToUtf8PathBufError::ToFilePathFailed)?;
-    let utf8_path = Utf8PathBuf::from_path_buf(path).map_err(|_| FileUrlStringToUtf8PathBufError::PathNotUtf8)?;
+    let path = url.to_file_path().map_err(|_| FileUrlConversionError::ConversionFailed)?;
+    let utf8_path = Utf8PathBuf::from_path_buf(path).map_err(|_| FileUrlConversionError::PathNotUtf8)?;
     Ok(utf8_path)
 }
 
+/// Converts an absolute [`Utf8Path`] into the `file:` URL string Node expects
+/// from `import.meta.resolve` and `createRequire`, the inverse of
+/// [`file_url_string_to_utf8_path_buf`].
+pub fn utf8_path_to_file_url(path: &Utf8Path) -> Result<String, FileUrlConversionError> {
+    let url = Url::from_file_path(path.as_std_path()).map_err(|_| FileUrlConversionError::ConversionFailed)?;
+    Ok(url.to_string())
+}
+
 #[derive(Clone, Copy, From, Into)]
 #[repr(transparent)]
 pub(crate) struct EnvEqHash(pub Env);
@@ -145,6 +275,29 @@ fn require_for(env: EnvEqHash) -> Result<Rc<FunctionRef<&'static str, Unknown<'s
     Ok(require.into())
 }
 
+// `#[memoize]` (used by `require_for` above and `esm_helpers_for` below) only exposes a
+// whole-cache clear, with no way to evict a single `(env, id)` entry, so
+// `invalidate`/`invalidate_all` need their own `EnvEqHash`-keyed cache here
+// rather than reusing the macro.
+thread_local! {
+    static REQUIRE_CACHE: RefCell<HashMap<(EnvEqHash, String), Rc<Ref<Unknown<'static>>>>> = RefCell::new(HashMap::new());
+}
+
+fn resolved_module_for(env: &Env, id: &str) -> napi::Result<Rc<Ref<Unknown<'static>>>> {
+    let key = (EnvEqHash::from(env.clone()), id.to_owned());
+    if let Some(module_ref) = REQUIRE_CACHE.with(|cache| cache.borrow().get(&key).cloned()) {
+        return Ok(module_ref);
+    }
+    let require = require_for(env.clone().into()).map_err(|e| napi::Error::from_reason(e))?;
+    let require = require.borrow_back(env)?;
+    let module = require.call(id)?;
+    let module_ref: Rc<Ref<Unknown<'static>>> = Rc::new(module.create_ref()?);
+    REQUIRE_CACHE.with(|cache| {
+        cache.borrow_mut().insert(key, module_ref.clone());
+    });
+    Ok(module_ref)
+}
+
 #[memoize]
 fn esm_helpers_for(env: EnvEqHash) -> Result<Rc<ObjectRef>, String> {
     let env = env.0;
@@ -158,3 +311,76 @@ fn esm_helpers_for(env: EnvEqHash) -> Result<Rc<ObjectRef>, String> {
     let esm_helpers = esm_helpers.create_ref().map_err(|e| e.to_string())?;
     Ok(esm_helpers.into())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lexically_normalize_resolves_relative_segments() {
+        let normalized = lexically_normalize(Utf8Path::new("/a/b/../c/./d"));
+        assert_eq!(normalized, Utf8PathBuf::from("/a/c/d"));
+    }
+
+    #[test]
+    fn lexically_normalize_does_not_escape_root() {
+        let normalized = lexically_normalize(Utf8Path::new("/a/../../../etc/passwd"));
+        assert_eq!(normalized, Utf8PathBuf::from("/etc/passwd"));
+    }
+
+    #[test]
+    fn lexically_normalize_keeps_unrooted_leading_parent_dirs() {
+        let normalized = lexically_normalize(Utf8Path::new("../../a"));
+        assert_eq!(normalized, Utf8PathBuf::from("../../a"));
+    }
+
+    #[test]
+    fn traversal_outside_base_is_detected() {
+        let base = lexically_normalize(Utf8Path::new("/sandbox"));
+        let escaped = lexically_normalize(Utf8Path::new("/sandbox/../../etc/passwd"));
+        assert!(!escaped.starts_with(&base));
+        let contained = lexically_normalize(Utf8Path::new("/sandbox/./pkg/../data.json"));
+        assert!(contained.starts_with(&base));
+    }
+
+    #[test]
+    fn utf8_path_to_file_url_round_trips() {
+        let path = Utf8Path::new("/a/b/c.js");
+        let file_url = utf8_path_to_file_url(path).unwrap();
+        assert_eq!(file_url, "file:///a/b/c.js");
+        let round_tripped = file_url_string_to_utf8_path_buf(&file_url).unwrap();
+        assert_eq!(round_tripped, path);
+    }
+
+    #[test]
+    fn utf8_path_to_file_url_percent_encodes_reserved_characters() {
+        let file_url = utf8_path_to_file_url(Utf8Path::new("/a b/c#d.js")).unwrap();
+        assert_eq!(file_url, "file:///a%20b/c%23d.js");
+    }
+
+    #[test]
+    fn file_url_string_to_utf8_path_buf_rejects_non_file_scheme() {
+        let error = file_url_string_to_utf8_path_buf("https://example.com/a.js").unwrap_err();
+        assert_eq!(error, FileUrlConversionError::SchemeNotFile);
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn utf8_path_to_file_url_round_trips_drive_letter() {
+        let path = Utf8Path::new(r"C:\Users\a\b.js");
+        let file_url = utf8_path_to_file_url(path).unwrap();
+        assert_eq!(file_url, "file:///C:/Users/a/b.js");
+        let round_tripped = file_url_string_to_utf8_path_buf(&file_url).unwrap();
+        assert_eq!(round_tripped, path);
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn utf8_path_to_file_url_round_trips_unc_path() {
+        let path = Utf8Path::new(r"\\server\share\a.js");
+        let file_url = utf8_path_to_file_url(path).unwrap();
+        assert_eq!(file_url, "file://server/share/a.js");
+        let round_tripped = file_url_string_to_utf8_path_buf(&file_url).unwrap();
+        assert_eq!(round_tripped, path);
+    }
+}
@@ -1,3 +1,4 @@
+use camino::Utf8Path;
 use std::{env, error::Error, process::Command};
 
 #[test]
@@ -8,13 +9,9 @@ fn test_greet() -> Result<(), Box<dyn Error>> {
     if !status.success() {
         return Err(format!("build examples-greet failed: {:?}", status).into());
     }
-    _ = fs_err::remove_file("target/debug/examples_greet.node");
-    fs_err::rename(
-        "target/debug/libexamples_greet.so",
-        "target/debug/examples_greet.node",
-    )?;
+    let node_path = napi_modules::build::link_node_addon("examples-greet", "debug", Utf8Path::new("target"))?;
     let status = Command::new("node")
-        .args(&["target/debug/examples_greet.node", "Alan Turing"])
+        .args(&[node_path.as_str(), "Alan Turing"])
         .status()?;
     if !status.success() {
         return Err(format!("run node failed: {:?}", status).into());
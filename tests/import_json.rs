@@ -0,0 +1,38 @@
+use camino::Utf8Path;
+use std::{env, error::Error, process::Command};
+
+/// Drives `import_json` and `import_with` against a real `.json` file, since
+/// both are async JS-interop paths that only a live Node runtime can exercise.
+#[test]
+fn test_import_json_and_import_with() -> Result<(), Box<dyn Error>> {
+    let status = Command::new(env::var("CARGO")?)
+        .args(&["build", "--package=examples-esm-json"])
+        .status()?;
+    if !status.success() {
+        return Err(format!("build examples-esm-json failed: {:?}", status).into());
+    }
+    // `./`-prefixed so Node's `require()` treats it as a path, not a package name.
+    let node_path = Utf8Path::new(".").join(napi_modules::build::link_node_addon("examples-esm-json", "debug", Utf8Path::new("target"))?);
+    let fixture_path = node_path.with_file_name("fixture.json");
+    fs_err::write(&fixture_path, r#"{"name":"napi-modules"}"#)?;
+
+    const SCRIPT_TEMPLATE: &str = r#"
+        const assert = require("node:assert");
+        const addon = require(__ADDON__);
+
+        (async () => {
+            assert.strictEqual(await addon.importJsonName(), "napi-modules");
+            assert.strictEqual(await addon.importWithName(), "napi-modules");
+        })().catch((error) => {
+            console.error(error);
+            process.exit(1);
+        });
+    "#;
+    let script = SCRIPT_TEMPLATE.replace("__ADDON__", &format!("{:?}", node_path.as_str()));
+
+    let status = Command::new("node").args(&["-e", &script]).status()?;
+    if !status.success() {
+        return Err(format!("node import_json/import_with check failed: {:?}", status).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use camino::Utf8Path;
+use std::{env, error::Error, process::Command};
+
+/// Proves three things about `EnvExt`'s `require()` cache against a real Node
+/// runtime (plain `cargo test` unit tests can't construct an `Env`):
+/// - `require()` keeps serving the same cached module across calls even after
+///   the underlying file changes and Node's own `require.cache` entry is
+///   cleared (i.e. the cache is actually consulted, not a no-op).
+/// - `invalidate(id)` forces re-resolution for that id on the calling addon.
+/// - `invalidate_all()` only clears the calling addon's own cache, leaving a
+///   second addon instance's cached modules untouched.
+#[test]
+fn test_require_cache_invalidate() -> Result<(), Box<dyn Error>> {
+    let status = Command::new(env::var("CARGO")?)
+        .args(&["build", "--package=examples-require-cache"])
+        .status()?;
+    if !status.success() {
+        return Err(format!("build examples-require-cache failed: {:?}", status).into());
+    }
+    // `./`-prefixed so Node's `require()` treats it as a path, not a package name.
+    let node_path = Utf8Path::new(".").join(napi_modules::build::link_node_addon("examples-require-cache", "debug", Utf8Path::new("target"))?);
+    let node_path_2 = node_path.with_file_name("examples_require_cache_2.node");
+    fs_err::copy(&node_path, &node_path_2)?;
+    let fixture_path = node_path.with_file_name("fixture.js");
+    fs_err::write(&fixture_path, "module.exports = { value: 1 };")?;
+
+    const SCRIPT_TEMPLATE: &str = r#"
+        const assert = require("node:assert");
+        const a = require(__A__);
+        const b = require(__B__);
+
+        assert.strictEqual(a.readValue(), 1);
+        assert.strictEqual(b.readValue(), 1);
+
+        // Simulate a hot-reloaded fixture: the file on disk changes and
+        // Node's own require cache is cleared, but our Rust-side cache
+        // should still be serving the stale value until invalidated.
+        require("node:fs").writeFileSync(__FIXTURE__, "module.exports = { value: 2 };");
+        delete require.cache[require.resolve(__FIXTURE__)];
+        assert.strictEqual(a.readValue(), 1);
+        assert.strictEqual(b.readValue(), 1);
+
+        // Invalidating `a` alone forces it to re-resolve, without disturbing `b`.
+        a.invalidateFixture();
+        assert.strictEqual(a.readValue(), 2);
+        assert.strictEqual(b.readValue(), 1);
+
+        // `invalidateAllFixtures` only clears the calling env's own cache.
+        require("node:fs").writeFileSync(__FIXTURE__, "module.exports = { value: 3 };");
+        delete require.cache[require.resolve(__FIXTURE__)];
+        b.invalidateAllFixtures();
+        assert.strictEqual(b.readValue(), 3);
+        assert.strictEqual(a.readValue(), 2);
+    "#;
+    let script = SCRIPT_TEMPLATE
+        .replace("__A__", &format!("{:?}", node_path.as_str()))
+        .replace("__B__", &format!("{:?}", node_path_2.as_str()))
+        .replace("__FIXTURE__", &format!("{:?}", fixture_path.as_str()));
+
+    let status = Command::new("node").args(&["-e", &script]).status()?;
+    if !status.success() {
+        return Err(format!("node require cache check failed: {:?}", status).into());
+    }
+    Ok(())
+}